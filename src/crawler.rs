@@ -1,24 +1,413 @@
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+use regex::Regex;
 use scraper::{Html, Selector};
 use serde::{Deserialize, Serialize};
-use futures::future;
 use url::Url;
 
+const DEFAULT_MAX_CONCURRENCY: usize = 100;
+const DEFAULT_USER_AGENT: &str = "rusty-crawler";
+const DEFAULT_MIN_DELAY: Duration = Duration::from_secs(1);
+const DEFAULT_MAX_RETRIES: usize = 3;
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(60);
+
+fn default_max_concurrency() -> usize {
+    DEFAULT_MAX_CONCURRENCY
+}
+
+fn default_user_agent() -> String {
+    DEFAULT_USER_AGENT.to_string()
+}
+
+fn default_min_delay() -> Duration {
+    DEFAULT_MIN_DELAY
+}
+
+fn default_scope() -> CrawlScope {
+    CrawlScope::default()
+}
+
+fn default_max_retries() -> usize {
+    DEFAULT_MAX_RETRIES
+}
+
+fn default_max_depth() -> usize {
+    usize::MAX
+}
+
+/// How far from the seed a crawl is allowed to wander, independent of the
+/// [`allow`](CrawlScope::allow)/[`deny`](CrawlScope::deny) pattern lists.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ScopeMode {
+    /// Follow any link, restricted only by the allow/deny lists.
+    Anywhere,
+    /// Stay on the seed URL's host.
+    SameHost,
+    /// Stay under the seed URL's path prefix, as a directory-style crawl.
+    PathPrefix,
+}
+
+/// The serde shape of a [`CrawlScope`]: only the declarative configuration,
+/// without the compiled [`Regex`] lists, which are rebuilt on load.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct ScopeConfig {
+    mode: ScopeMode,
+    #[serde(default)]
+    base: Option<String>,
+    #[serde(default)]
+    allow: Vec<String>,
+    #[serde(default)]
+    deny: Vec<String>,
+}
+
+/// Decides which discovered links are in bounds for the crawl. A link must
+/// satisfy the [`ScopeMode`], not match any `deny` pattern, and — when the
+/// `allow` list is non-empty — match at least one `allow` pattern.
+///
+/// The `allow`/`deny` patterns are compiled to [`Regex`] once, when the scope
+/// is built, and an invalid pattern is reported then rather than silently
+/// treated as a non-match on the hot per-link path.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "ScopeConfig", into = "ScopeConfig")]
+pub struct CrawlScope {
+    mode: ScopeMode,
+    base: Option<String>,
+    allow: Vec<String>,
+    deny: Vec<String>,
+    allow_re: Vec<Regex>,
+    deny_re: Vec<Regex>,
+}
+
+impl Default for CrawlScope {
+    fn default() -> Self {
+        CrawlScope {
+            mode: ScopeMode::Anywhere,
+            base: None,
+            allow: Vec::new(),
+            deny: Vec::new(),
+            allow_re: Vec::new(),
+            deny_re: Vec::new(),
+        }
+    }
+}
+
+impl TryFrom<ScopeConfig> for CrawlScope {
+    type Error = regex::Error;
+
+    fn try_from(config: ScopeConfig) -> std::result::Result<Self, Self::Error> {
+        let allow_re = compile_patterns(&config.allow)?;
+        let deny_re = compile_patterns(&config.deny)?;
+        Ok(CrawlScope {
+            mode: config.mode,
+            base: config.base,
+            allow: config.allow,
+            deny: config.deny,
+            allow_re,
+            deny_re,
+        })
+    }
+}
+
+impl From<CrawlScope> for ScopeConfig {
+    fn from(scope: CrawlScope) -> Self {
+        ScopeConfig {
+            mode: scope.mode,
+            base: scope.base,
+            allow: scope.allow,
+            deny: scope.deny,
+        }
+    }
+}
+
+/// Compile each pattern, returning the first compile error instead of
+/// swallowing it.
+fn compile_patterns(patterns: &[String]) -> std::result::Result<Vec<Regex>, regex::Error> {
+    patterns.iter().map(|p| Regex::new(p)).collect()
+}
+
+impl CrawlScope {
+    /// Restrict the crawl to the seed URL's host.
+    pub fn same_host(base: &str) -> Self {
+        CrawlScope {
+            mode: ScopeMode::SameHost,
+            base: Some(base.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Restrict the crawl to links under the seed URL's path prefix.
+    pub fn path_prefix(base: &str) -> Self {
+        CrawlScope {
+            mode: ScopeMode::PathPrefix,
+            base: Some(base.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// Add a regex a URL must match to stay in scope. Panics if `pattern` is
+    /// not a valid regular expression.
+    pub fn allow(mut self, pattern: &str) -> Self {
+        let re = Regex::new(pattern)
+            .unwrap_or_else(|err| panic!("invalid allow pattern {pattern:?}: {err}"));
+        self.allow.push(pattern.to_string());
+        self.allow_re.push(re);
+        self
+    }
+
+    /// Add a regex a URL must not match to stay in scope. Panics if `pattern`
+    /// is not a valid regular expression.
+    pub fn deny(mut self, pattern: &str) -> Self {
+        let re = Regex::new(pattern)
+            .unwrap_or_else(|err| panic!("invalid deny pattern {pattern:?}: {err}"));
+        self.deny.push(pattern.to_string());
+        self.deny_re.push(re);
+        self
+    }
+
+    fn base_url(&self) -> Option<Url> {
+        self.base.as_deref().and_then(|b| Url::parse(b).ok())
+    }
+
+    /// Whether `url` falls within the configured scope.
+    fn allows(&self, url: &Url) -> bool {
+        let target = url.as_str();
+
+        if self.deny_re.iter().any(|re| re.is_match(target)) {
+            return false;
+        }
+
+        if !self.allow_re.is_empty() && !self.allow_re.iter().any(|re| re.is_match(target)) {
+            return false;
+        }
+
+        match self.mode {
+            ScopeMode::Anywhere => true,
+            ScopeMode::SameHost => self
+                .base_url()
+                .and_then(|base| Some(base.host_str()? == url.host_str()?))
+                .unwrap_or(true),
+            ScopeMode::PathPrefix => self
+                .base_url()
+                .map(|base| {
+                    // Require a path-segment boundary so that base `…/docs`
+                    // matches `…/docs` and `…/docs/x` but not `…/docs-evil/`.
+                    let base = base.as_str();
+                    let stem = base.strip_suffix('/').unwrap_or(base);
+                    target == stem || target.starts_with(&format!("{stem}/"))
+                })
+                .unwrap_or(true),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Crawler {
     #[serde(skip)]
     client: reqwest::Client,
     graph: HashMap::<String, Vec<String>>,
-    queue: VecDeque<String>
+    /// Distance in hops from the nearest seed for every URL the crawler has
+    /// enqueued, used to enforce [`max_depth`](Crawler::max_depth).
+    #[serde(default)]
+    depth: HashMap<String, usize>,
+    queue: VecDeque<(String, usize)>,
+    #[serde(default = "default_max_concurrency")]
+    max_concurrency: usize,
+    #[serde(default = "default_user_agent")]
+    user_agent: String,
+    #[serde(default = "default_min_delay")]
+    min_delay: Duration,
+    /// Per-host robots.txt rules, fetched lazily the first time a host is seen.
+    #[serde(skip)]
+    robots: HashMap<String, RobotsRules>,
+    /// Last time a request was issued to each host, used to space out fetches.
+    #[serde(skip)]
+    last_request: HashMap<String, Instant>,
+    #[serde(default = "default_scope")]
+    scope: CrawlScope,
+    #[serde(default = "default_max_retries")]
+    max_retries: usize,
+    /// URLs whose retries were exhausted, paired with the last error seen.
+    #[serde(default)]
+    failed: HashMap<String, CrawlerError>,
+    /// Retry attempts spent per URL so far in this run.
+    #[serde(skip)]
+    attempts: HashMap<String, usize>,
+    /// Stop starting new fetches once this many pages have been explored.
+    #[serde(default)]
+    max_pages: Option<usize>,
+    /// Do not enqueue links more than this many hops from a seed.
+    #[serde(default = "default_max_depth")]
+    max_depth: usize,
+    /// When set, fetched page bodies are mirrored into this directory.
+    #[serde(default)]
+    output_dir: Option<PathBuf>,
+}
+
+/// The subset of a `robots.txt` file the crawler enforces: the `Disallow`
+/// prefixes that apply to the configured user-agent and an optional
+/// `Crawl-delay` that overrides the crawler's default minimum spacing.
+#[derive(Clone, Debug, Default)]
+struct RobotsRules {
+    disallow: Vec<String>,
+    crawl_delay: Option<Duration>,
+}
+
+impl RobotsRules {
+    /// Parse the rules that apply to `user_agent`, preferring a group that
+    /// names the agent exactly and otherwise falling back to the `*` group.
+    fn parse(body: &str, user_agent: &str) -> Self {
+        let ua = user_agent.to_ascii_lowercase();
+        let mut specific = RobotsRules::default();
+        let mut wildcard = RobotsRules::default();
+        let mut have_specific = false;
+
+        let mut current_agents: Vec<String> = Vec::new();
+        let mut last_was_agent = false;
+
+        for line in body.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (field, value) = match line.split_once(':') {
+                Some((f, v)) => (f.trim().to_ascii_lowercase(), v.trim().to_string()),
+                None => continue,
+            };
+
+            match field.as_str() {
+                "user-agent" => {
+                    // A run of consecutive `User-agent` lines targets one group.
+                    if !last_was_agent {
+                        current_agents.clear();
+                    }
+                    current_agents.push(value.to_ascii_lowercase());
+                    last_was_agent = true;
+                }
+                "disallow" => {
+                    last_was_agent = false;
+                    for agent in &current_agents {
+                        if *agent == ua {
+                            specific.disallow.push(value.clone());
+                            have_specific = true;
+                        } else if agent == "*" {
+                            wildcard.disallow.push(value.clone());
+                        }
+                    }
+                }
+                "crawl-delay" => {
+                    last_was_agent = false;
+                    if let Ok(secs) = value.parse::<f64>() {
+                        let delay = Duration::from_secs_f64(secs);
+                        for agent in &current_agents {
+                            if *agent == ua {
+                                specific.crawl_delay = Some(delay);
+                                have_specific = true;
+                            } else if agent == "*" {
+                                wildcard.crawl_delay = Some(delay);
+                            }
+                        }
+                    }
+                }
+                _ => last_was_agent = false,
+            }
+        }
+
+        if have_specific { specific } else { wildcard }
+    }
+
+    /// Whether `path` is permitted, i.e. not covered by any `Disallow` prefix.
+    fn allows(&self, path: &str) -> bool {
+        !self
+            .disallow
+            .iter()
+            .any(|rule| !rule.is_empty() && path.starts_with(rule.as_str()))
+    }
+}
+
+/// A minimal async permit pool bounding how many requests are in flight at once.
+///
+/// `available` counts free slots; [`acquire`](Limiter::acquire) claims one
+/// (awaiting when none are left) and hands back a [`Permit`] whose `Drop`
+/// returns the slot to the pool so a waiting task can pick it up immediately.
+#[derive(Clone)]
+struct Limiter {
+    available: Arc<AtomicU32>,
+}
+
+struct Permit {
+    available: Arc<AtomicU32>,
+}
+
+impl Limiter {
+    fn new(max: usize) -> Self {
+        Limiter {
+            available: Arc::new(AtomicU32::new(max as u32)),
+        }
+    }
+
+    /// Claim a slot, yielding until one frees up if the pool is exhausted.
+    async fn acquire(&self) -> Permit {
+        loop {
+            let current = self.available.load(Ordering::Acquire);
+            if current > 0
+                && self
+                    .available
+                    .compare_exchange(current, current - 1, Ordering::AcqRel, Ordering::Acquire)
+                    .is_ok()
+            {
+                return Permit {
+                    available: Arc::clone(&self.available),
+                };
+            }
+            tokio::task::yield_now().await;
+        }
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.available.fetch_add(1, Ordering::Release);
+    }
 }
 
 pub type Result<T> = std::result::Result<T, CrawlerError>;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum CrawlerError {
     RequestError,
     EmptyQueue,
     UrlParseError,
+    /// A transient failure (network error, 5xx, or 429) that is worth
+    /// retrying, carrying any `Retry-After` hint the server returned.
+    Transient { retry_after: Option<Duration> },
+    /// A non-retryable HTTP status (e.g. 4xx other than 429).
+    HttpStatus(u16),
+}
+
+/// What a spawned fetch task reports back to the scheduler.
+enum FetchOutcome {
+    /// The page was fetched; carries its URL, depth, and body.
+    Ok { url: String, depth: usize, body: String },
+    /// The fetch failed transiently and should be re-queued at the same depth.
+    Retry { url: String, depth: usize },
+    /// The fetch failed terminally and should be recorded in `failed`.
+    Failed { url: String, error: CrawlerError },
+}
+
+/// Backoff before the `attempt`-th retry: 1s doubled each attempt, capped.
+fn retry_backoff(attempt: usize) -> Duration {
+    let factor = 1u32.checked_shl(attempt as u32).unwrap_or(u32::MAX);
+    RETRY_BASE_DELAY
+        .saturating_mul(factor)
+        .min(RETRY_MAX_DELAY)
 }
 
 impl Crawler {
@@ -26,22 +415,205 @@ impl Crawler {
         let mut crawler = Crawler {
             client: reqwest::Client::new(),
             graph: HashMap::<String, Vec<String>>::new(),
-            queue: VecDeque::<String>::new()
+            depth: HashMap::new(),
+            queue: VecDeque::new(),
+            max_concurrency: DEFAULT_MAX_CONCURRENCY,
+            user_agent: default_user_agent(),
+            min_delay: DEFAULT_MIN_DELAY,
+            robots: HashMap::new(),
+            last_request: HashMap::new(),
+            scope: CrawlScope::default(),
+            max_retries: DEFAULT_MAX_RETRIES,
+            failed: HashMap::new(),
+            attempts: HashMap::new(),
+            max_pages: None,
+            max_depth: usize::MAX,
+            output_dir: None,
         };
 
-        crawler.queue.push_front(url);
+        crawler.depth.insert(url.clone(), 0);
+        crawler.queue.push_front((url, 0));
         crawler
     }
 
+    /// Restrict which discovered links are enqueued. Out-of-scope URLs are
+    /// dropped before they ever reach the queue.
+    pub fn scope(mut self, scope: CrawlScope) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    /// Enqueue the in-scope links discovered on a page at `parent_depth`,
+    /// assigning each child `parent_depth + 1` and dropping any that would
+    /// exceed [`max_depth`](Crawler::max_depth).
+    fn enqueue_links(&mut self, links: &[String], parent_depth: usize) {
+        let child_depth = parent_depth + 1;
+        if child_depth > self.max_depth {
+            return;
+        }
+
+        for link in links {
+            if Url::parse(link).is_ok_and(|url| self.scope.allows(&url)) {
+                let known = self.depth.entry(link.clone()).or_insert(child_depth);
+                *known = (*known).min(child_depth);
+                self.queue.push_back((link.clone(), child_depth));
+            }
+        }
+    }
+
+    /// Set the maximum number of requests kept in flight at once.
+    pub fn max_concurrency(mut self, max: usize) -> Self {
+        self.max_concurrency = max;
+        self
+    }
+
+    /// Set how many times a transiently-failing URL is retried before it is
+    /// recorded in [`failed`](Crawler::failed).
+    pub fn max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// URLs whose retries were exhausted, paired with the last error seen.
+    pub fn failed(&self) -> &HashMap<String, CrawlerError> {
+        &self.failed
+    }
+
+    /// Set the user-agent the crawler identifies as. This both rebuilds the
+    /// HTTP client so the header is sent on every request and drives which
+    /// `robots.txt` groups apply.
+    pub fn user_agent(mut self, user_agent: String) -> Self {
+        self.client = reqwest::Client::builder()
+            .user_agent(&user_agent)
+            .build()
+            .unwrap_or_default();
+        self.user_agent = user_agent;
+        self
+    }
+
+    /// Stop starting new fetches once this many pages have been explored.
+    pub fn max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = Some(max_pages);
+        self
+    }
+
+    /// Set the maximum crawl depth; links beyond it are not enqueued.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Mirror fetched page bodies into `path`, one file per URL, so the crawl
+    /// produces an offline copy of the site alongside its link graph. Already
+    /// explored URLs (the keys of [`graph`](Crawler::graph)) are skipped on a
+    /// resumed run, so re-runs never re-download them.
+    pub fn with_output_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.output_dir = Some(path.into());
+        self
+    }
+
+    /// Write `body` to the content store, if one is configured. Failures are
+    /// logged but never abort the crawl.
+    fn store_body(&self, url: &str, body: &str) {
+        let Some(dir) = &self.output_dir else {
+            return;
+        };
+
+        if let Err(err) = fs::create_dir_all(dir) {
+            eprintln!("failed to create output dir {}: {err}", dir.display());
+            return;
+        }
+
+        let path = dir.join(body_filename(url));
+        if let Err(err) = fs::write(&path, body) {
+            eprintln!("failed to write {}: {err}", path.display());
+        }
+    }
+
+    /// Set the minimum delay enforced between requests to the same host. A
+    /// host's `Crawl-delay` directive, when larger, takes precedence.
+    pub fn min_delay(mut self, min_delay: Duration) -> Self {
+        self.min_delay = min_delay;
+        self
+    }
+
+    /// Fetch and cache the `robots.txt` rules for `host`, deriving the
+    /// robots URL from `base`. Hosts whose `robots.txt` is missing or
+    /// unreachable are cached as permissive.
+    async fn robots_for(&mut self, host: &str, base: &Url) -> RobotsRules {
+        if let Some(rules) = self.robots.get(host) {
+            return rules.clone();
+        }
+
+        let rules = match base.join("/robots.txt") {
+            Ok(robots_url) => match make_request(&self.client, robots_url.as_str()).await {
+                Ok(body) => RobotsRules::parse(&body, &self.user_agent),
+                Err(_) => RobotsRules::default(),
+            },
+            Err(_) => RobotsRules::default(),
+        };
+
+        self.robots.insert(host.to_string(), rules.clone());
+        rules
+    }
+
+    /// Wait out the remaining crawl delay for `host`, then record the current
+    /// instant as its last request time. `crawl_delay` overrides `min_delay`
+    /// when it is the larger of the two.
+    async fn await_politeness(&mut self, host: &str, crawl_delay: Option<Duration>) {
+        let delay = crawl_delay.map_or(self.min_delay, |d| d.max(self.min_delay));
+        if let Some(last) = self.last_request.get(host) {
+            let elapsed = last.elapsed();
+            if elapsed < delay {
+                tokio::time::sleep(delay - elapsed).await;
+            }
+        }
+        self.last_request.insert(host.to_string(), Instant::now());
+    }
+
+    fn next_queued(&mut self) -> Option<(String, usize)> {
+        while let Some((url, depth)) = self.queue.pop_front() {
+            if !self.graph.contains_key(&url) {
+                return Some((url, depth));
+            }
+        }
+        None
+    }
+
     pub async fn explore_url(&mut self, url: String) -> Result<()> {
         if let Ok(parsed_url) = Url::parse(&url) {
-            let response = make_request(&self.client, parsed_url.as_str()).await
-                .or(Err(CrawlerError::RequestError))?;
+            let host = parsed_url.host_str().unwrap_or_default().to_string();
+            let rules = self.robots_for(&host, &parsed_url).await;
+            if !rules.allows(parsed_url.path()) {
+                // Disallowed by robots.txt; record the node with no links so
+                // it is not revisited and move on.
+                self.graph.insert(url, Vec::new());
+                return Ok(());
+            }
+            self.await_politeness(&host, rules.crawl_delay).await;
 
-            let links = extract_hrefs_from(&parsed_url.to_string(), &response);
-            let mut links_deque = VecDeque::<String>::from(links.clone());
+            // Retry transient failures with exponential backoff, recording the
+            // URL as failed once its attempts are exhausted.
+            let mut attempt = 0;
+            let response = loop {
+                match make_request(&self.client, parsed_url.as_str()).await {
+                    Ok(body) => break body,
+                    Err(CrawlerError::Transient { retry_after }) if attempt < self.max_retries => {
+                        let delay = retry_after.unwrap_or_else(|| retry_backoff(attempt));
+                        tokio::time::sleep(delay).await;
+                        attempt += 1;
+                    }
+                    Err(error) => {
+                        self.failed.insert(url, error.clone());
+                        return Err(error);
+                    }
+                }
+            };
 
-            self.queue.append(&mut links_deque);
+            self.store_body(&url, &response);
+            let parent_depth = *self.depth.get(&url).unwrap_or(&0);
+            let links = extract_hrefs_from(parsed_url.as_str(), &response);
+            self.enqueue_links(&links, parent_depth);
             self.graph.insert(url, links);
             Ok(())
         } else {
@@ -50,7 +622,7 @@ impl Crawler {
     }
 
     pub async fn explore_queue(&mut self, ignore_already_crawled: bool) -> Result<()> {
-        if let Some(url) = self.queue.pop_front() {
+        if let Some((url, _depth)) = self.queue.pop_front() {
             if !self.graph.contains_key(&url) || !ignore_already_crawled {
                 self.explore_url(url).await?;
             }
@@ -60,24 +632,87 @@ impl Crawler {
         }
     }
 
-    pub async fn explore_queue_multi(&mut self, n: usize) -> Result<()> {
-        let urls = self.queue.drain(0..n);
+    pub async fn explore_queue_multi(&mut self) -> Result<()> {
+        let limiter = Limiter::new(self.max_concurrency);
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut in_flight: usize = 0;
+
+        loop {
+            // Keep the pool fed, but never let more than `max_concurrency`
+            // fetches be outstanding: claim a permit and spawn a request for
+            // each queued URL until we hit that ceiling, run out of work, or
+            // reach the page budget (counting work already in flight). Capping
+            // in-flight work bounds the channel so completed page bodies are
+            // drained each iteration instead of accumulating in memory.
+            while in_flight < self.max_concurrency
+                && self
+                    .max_pages
+                    .is_none_or(|max| self.graph.len() + in_flight < max)
+            {
+                let (url, depth) = match self.next_queued() {
+                    Some(entry) => entry,
+                    None => break,
+                };
+                // Honour robots.txt and per-host spacing on the scheduling
+                // thread so the shared caches stay behind `&mut self`.
+                let parsed = match Url::parse(&url) {
+                    Ok(parsed) => parsed,
+                    Err(_) => continue,
+                };
+                let host = parsed.host_str().unwrap_or_default().to_string();
+                let rules = self.robots_for(&host, &parsed).await;
+                if !rules.allows(parsed.path()) {
+                    self.graph.insert(url, Vec::new());
+                    continue;
+                }
+                self.await_politeness(&host, rules.crawl_delay).await;
+
+                let permit = limiter.acquire().await;
+                let client = self.client.clone();
+                let tx = tx.clone();
+                let attempt = *self.attempts.get(&url).unwrap_or(&0);
+                let max_retries = self.max_retries;
+                in_flight += 1;
+                tokio::spawn(async move {
+                    let _permit = permit; // slot is released when this task ends
+                    let outcome = match make_request(&client, &url).await {
+                        Ok(body) => FetchOutcome::Ok { url, depth, body },
+                        Err(CrawlerError::Transient { retry_after }) if attempt < max_retries => {
+                            // Back off in-task so the scheduler stays free to
+                            // keep other hosts busy while this URL waits.
+                            let delay = retry_after.unwrap_or_else(|| retry_backoff(attempt));
+                            tokio::time::sleep(delay).await;
+                            FetchOutcome::Retry { url, depth }
+                        }
+                        Err(error) => FetchOutcome::Failed { url, error },
+                    };
+                    let _ = tx.send(outcome);
+                });
+            }
 
-        let responses = future::join_all(urls.map(|url| {
-            let client = &self.client;
-            async move {
-                client.get(url).send().await
+            if in_flight == 0 {
+                break;
             }
-        })).await;
-
-        for response in responses {
-            if let Ok(content) = response {
-                let url = content.url().to_string();
-                let body = content.text().await.unwrap();
-                let links = extract_hrefs_from(&url, &body);
-                let mut links_deque = VecDeque::<String>::from(links.clone());
-                self.queue.append(&mut links_deque);
-                self.graph.insert(url, links);
+
+            if let Some(outcome) = rx.recv().await {
+                in_flight -= 1;
+                match outcome {
+                    FetchOutcome::Ok { url, depth, body } => {
+                        self.attempts.remove(&url);
+                        self.store_body(&url, &body);
+                        let links = extract_hrefs_from(&url, &body);
+                        self.enqueue_links(&links, depth);
+                        self.graph.insert(url, links);
+                    }
+                    FetchOutcome::Retry { url, depth } => {
+                        *self.attempts.entry(url.clone()).or_insert(0) += 1;
+                        self.queue.push_back((url, depth));
+                    }
+                    FetchOutcome::Failed { url, error } => {
+                        self.attempts.remove(&url);
+                        self.failed.insert(url, error);
+                    }
+                }
             }
         }
 
@@ -85,12 +720,18 @@ impl Crawler {
     }
 
     pub fn add_to_queue(&mut self, url: String) {
-        self.queue.push_back(url);
+        self.depth.insert(url.clone(), 0);
+        self.queue.push_back((url, 0));
     }
 
     pub fn explored_nodes(&self) -> usize {
         self.graph.len()
     }
+
+    /// The adjacency map of explored pages to the links found on them.
+    pub fn graph(&self) -> &HashMap<String, Vec<String>> {
+        &self.graph
+    }
 }
 
 fn extract_hrefs_from(url: &str, body: &str) -> Vec<String> {
@@ -114,12 +755,45 @@ fn extract_hrefs_from(url: &str, body: &str) -> Vec<String> {
         .collect()
 }
 
+/// A filesystem-safe, collision-resistant file name for a page URL: a short
+/// alphanumeric slug of the URL plus a hash of the full URL to disambiguate.
+fn body_filename(url: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    let digest = hasher.finish();
+
+    let slug: String = url
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .take(80)
+        .collect();
+
+    format!("{slug}-{digest:016x}.html")
+}
+
 async fn make_request(client: &reqwest::Client, url: &str) -> Result<String>  {
-    client.get(url)
+    let response = client.get(url)
         .send()
-        .await.unwrap()
-        .text()
-        .await.or(Err(CrawlerError::RequestError))
+        .await
+        .or(Err(CrawlerError::Transient { retry_after: None }))?;
+
+    let status = response.status();
+    if status.is_success() {
+        return response.text().await.or(Err(CrawlerError::RequestError));
+    }
+
+    if status.as_u16() == 429 || status.is_server_error() {
+        // Honour an explicit `Retry-After` (seconds) when the server sends one.
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+            .map(Duration::from_secs);
+        return Err(CrawlerError::Transient { retry_after });
+    }
+
+    Err(CrawlerError::HttpStatus(status.as_u16()))
 }
 
 #[cfg(test)]
@@ -166,4 +840,76 @@ mod tests {
 
         assert_eq!(links, ["https://www.example.com/examples/"]);
     }
+
+    #[test]
+    fn robots_prefers_specific_group_and_strips_comments() {
+        let body = "\
+            User-agent: *\n\
+            Disallow: /everyone\n\
+            \n\
+            User-agent: rusty-crawler\n\
+            Disallow: /private  # keep out\n\
+            Crawl-delay: 5\n";
+
+        let rules = RobotsRules::parse(body, "rusty-crawler");
+
+        assert_eq!(rules.disallow, ["/private"]);
+        assert_eq!(rules.crawl_delay, Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn robots_falls_back_to_wildcard_group() {
+        let body = "\
+            User-agent: *\n\
+            Disallow: /blocked\n";
+
+        let rules = RobotsRules::parse(body, "rusty-crawler");
+
+        assert!(rules.allows("/allowed"));
+        assert!(!rules.allows("/blocked/page"));
+    }
+
+    #[test]
+    fn scope_same_host_and_path_prefix() {
+        let host = CrawlScope::same_host("https://example.com/docs");
+        assert!(host.allows(&Url::parse("https://example.com/other").unwrap()));
+        assert!(!host.allows(&Url::parse("https://elsewhere.com/").unwrap()));
+
+        let prefix = CrawlScope::path_prefix("https://example.com/docs");
+        assert!(prefix.allows(&Url::parse("https://example.com/docs").unwrap()));
+        assert!(prefix.allows(&Url::parse("https://example.com/docs/page").unwrap()));
+        // A sibling sharing the prefix as a substring must stay out of scope.
+        assert!(!prefix.allows(&Url::parse("https://example.com/docs-evil/").unwrap()));
+    }
+
+    #[test]
+    fn scope_allow_and_deny_patterns() {
+        let scope = CrawlScope::default().allow(r"/blog/").deny(r"\.pdf$");
+        assert!(scope.allows(&Url::parse("https://example.com/blog/post").unwrap()));
+        // Not matched by any allow pattern.
+        assert!(!scope.allows(&Url::parse("https://example.com/about").unwrap()));
+        // Matched by a deny pattern even though it also matches allow.
+        assert!(!scope.allows(&Url::parse("https://example.com/blog/doc.pdf").unwrap()));
+    }
+
+    #[test]
+    fn retry_backoff_doubles_and_caps() {
+        assert_eq!(retry_backoff(0), RETRY_BASE_DELAY);
+        assert_eq!(retry_backoff(1), RETRY_BASE_DELAY * 2);
+        assert_eq!(retry_backoff(2), RETRY_BASE_DELAY * 4);
+        // Far beyond the cap, and past where the shift would overflow.
+        assert_eq!(retry_backoff(1000), RETRY_MAX_DELAY);
+    }
+
+    #[test]
+    fn body_filename_is_deterministic_and_collision_resistant() {
+        let a = "https://example.com/a";
+        let b = "https://example.com/b";
+
+        assert_eq!(body_filename(a), body_filename(a));
+        assert_ne!(body_filename(a), body_filename(b));
+        assert!(body_filename(a).ends_with(".html"));
+        // No path-separator or other awkward characters leak into the name.
+        assert!(!body_filename("https://example.com/a/b?q=1").contains('/'));
+    }
 }
\ No newline at end of file
@@ -1,32 +1,211 @@
-use std::{fs, io};
+use std::error::Error;
+use std::fs;
+use std::io;
+
+use clap::{Parser, Subcommand, ValueEnum};
 
 pub mod crawler;
-use crawler::Crawler;
+use crawler::{CrawlScope, Crawler};
+
+#[derive(Parser)]
+#[command(name = "rusty-crawler", about = "A small async web crawler")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Crawl the web starting from one or more seed URLs.
+    Crawl(CrawlArgs),
+    /// Load a saved crawl and print a summary without fetching anything.
+    Report {
+        /// Path to the JSON state snapshot to load.
+        #[arg(long, default_value = "crawler.json")]
+        state: String,
+        /// How to print the loaded crawl.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Count)]
+        output: OutputFormat,
+    },
+}
+
+#[derive(clap::Args)]
+struct CrawlArgs {
+    /// Seed URL(s) to start from. When `--state` already holds a crawl, every
+    /// seed is added to its queue as an extra entry point.
+    seeds: Vec<String>,
+    /// Stop after this many pages have been explored.
+    #[arg(long)]
+    max_pages: Option<usize>,
+    /// Do not follow links more than this many hops from a seed.
+    #[arg(long)]
+    max_depth: Option<usize>,
+    /// Maximum number of requests kept in flight at once.
+    #[arg(long, default_value_t = 100)]
+    concurrency: usize,
+    /// User-agent string sent with each request.
+    #[arg(long, default_value = "rusty-crawler")]
+    user_agent: String,
+    /// Path of the JSON snapshot to resume from and save back to.
+    #[arg(long, default_value = "crawler.json")]
+    state: String,
+    /// Directory to mirror fetched page bodies into for offline use.
+    #[arg(long)]
+    output_dir: Option<String>,
+    /// Stay on the first seed's host.
+    #[arg(long)]
+    same_host: bool,
+    /// Stay under the first seed's path prefix, as a directory-style crawl.
+    #[arg(long, conflicts_with = "same_host")]
+    path_prefix: bool,
+    /// Regex a URL must match to stay in scope (repeatable).
+    #[arg(long)]
+    allow: Vec<String>,
+    /// Regex a URL must not match to stay in scope (repeatable).
+    #[arg(long)]
+    deny: Vec<String>,
+    /// How to print results once the crawl finishes.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Count)]
+    output: OutputFormat,
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum OutputFormat {
+    /// Print only the number of explored pages.
+    Count,
+    /// Print the full crawler state as JSON.
+    Json,
+    /// Print one `from -> to` edge per line.
+    Edges,
+}
 
 #[tokio::main]
-async fn main() -> reqwest::Result<()>{
-    let mut crawler: Crawler = if let Ok(crawler_json) = fs::File::open("crawler.json") {
-        let reader = io::BufReader::new(crawler_json);
-        serde_json::from_reader(reader)
-            .expect("Error deserializing crawler from IO buffer")
-    } else {
-        Crawler::new("https://en.wikipedia.org/wiki/Main_Page".to_string())
+async fn main() -> Result<(), Box<dyn Error>> {
+    match Cli::parse().command {
+        Command::Crawl(args) => run_crawl(args).await,
+        Command::Report { state, output } => {
+            let crawler = load_state(&state)?
+                .ok_or_else(|| format!("no saved crawl found at {state}"))?;
+            print_output(&crawler, output);
+            Ok(())
+        }
+    }
+}
+
+async fn run_crawl(args: CrawlArgs) -> Result<(), Box<dyn Error>> {
+    let resumed = load_state(&args.state)?;
+    let fresh = resumed.is_none();
+    let mut crawler = match resumed {
+        Some(crawler) => crawler,
+        None => {
+            let first = args
+                .seeds
+                .first()
+                .ok_or("at least one seed URL is required to start a new crawl")?;
+            Crawler::new(first.clone())
+        }
     };
 
-    for i in 0..1 {
-        println!("{}", i);
-        crawler.explore_queue(true).await
-            .expect("Failed to explore from queue");
+    // A fresh crawler already queued the first seed; a resumed one takes all
+    // of the seeds given on the command line as additional entry points.
+    let skip = if fresh { 1 } else { 0 };
+    for seed in args.seeds.iter().skip(skip) {
+        crawler.add_to_queue(seed.clone());
     }
 
-    crawler.explore_queue_multi(100).await
-        .expect("Failed to make async requests");
+    crawler = crawler
+        .max_concurrency(args.concurrency)
+        .user_agent(args.user_agent.clone());
+    if let Some(max_pages) = args.max_pages {
+        crawler = crawler.max_pages(max_pages);
+    }
+    if let Some(max_depth) = args.max_depth {
+        crawler = crawler.max_depth(max_depth);
+    }
+    if let Some(output_dir) = &args.output_dir {
+        crawler = crawler.with_output_dir(output_dir);
+    }
+    if let Some(scope) = build_scope(&args)? {
+        crawler = crawler.scope(scope);
+    }
 
-    println!("crawler has {} nodes", crawler.explored_nodes());
+    crawler
+        .explore_queue_multi()
+        .await
+        .map_err(|err| format!("crawl failed: {err:?}"))?;
 
-    let serialized = serde_json::to_string(&crawler).unwrap();
-    std::fs::write("crawler.json", serialized)
-        .expect("Failed to serialize crawler.");
+    let serialized = serde_json::to_string(&crawler)?;
+    fs::write(&args.state, serialized)?;
 
+    print_output(&crawler, args.output);
     Ok(())
 }
+
+/// Build the crawl scope requested on the command line, anchoring host and
+/// path-prefix restrictions to the first seed. Returns `None` when no scope
+/// flags were given, leaving the crawler's current scope (unrestricted for a
+/// fresh crawl, the saved one for a resumed crawl) in place.
+fn build_scope(args: &CrawlArgs) -> Result<Option<CrawlScope>, Box<dyn Error>> {
+    if !args.same_host && !args.path_prefix && args.allow.is_empty() && args.deny.is_empty() {
+        return Ok(None);
+    }
+
+    // Reject invalid patterns up front so a typo fails with a clear message
+    // rather than panicking inside the builder.
+    for pattern in args.allow.iter().chain(&args.deny) {
+        regex::Regex::new(pattern)
+            .map_err(|err| format!("invalid scope pattern {pattern:?}: {err}"))?;
+    }
+
+    let mut scope = if args.same_host || args.path_prefix {
+        let base = args.seeds.first().ok_or(
+            "--same-host/--path-prefix require a seed URL to anchor the scope",
+        )?;
+        if args.path_prefix {
+            CrawlScope::path_prefix(base)
+        } else {
+            CrawlScope::same_host(base)
+        }
+    } else {
+        CrawlScope::default()
+    };
+
+    for pattern in &args.allow {
+        scope = scope.allow(pattern);
+    }
+    for pattern in &args.deny {
+        scope = scope.deny(pattern);
+    }
+
+    Ok(Some(scope))
+}
+
+fn load_state(path: &str) -> Result<Option<Crawler>, Box<dyn Error>> {
+    match fs::File::open(path) {
+        Ok(file) => {
+            let reader = io::BufReader::new(file);
+            Ok(Some(serde_json::from_reader(reader)?))
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn print_output(crawler: &Crawler, output: OutputFormat) {
+    match output {
+        OutputFormat::Count => {
+            println!("crawler has {} nodes", crawler.explored_nodes());
+        }
+        OutputFormat::Json => match serde_json::to_string_pretty(crawler) {
+            Ok(json) => println!("{json}"),
+            Err(err) => eprintln!("failed to serialize crawler: {err}"),
+        },
+        OutputFormat::Edges => {
+            for (from, targets) in crawler.graph() {
+                for to in targets {
+                    println!("{from} -> {to}");
+                }
+            }
+        }
+    }
+}